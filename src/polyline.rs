@@ -1,8 +1,12 @@
 use crate::{material::PolylineMaterial, SHADER_HANDLE};
 use bevy::{
     core::cast_slice,
+    core_pipeline::prepass::{
+        DepthPrepass, MOTION_VECTOR_PREPASS_FORMAT, MotionVectorPrepass, NORMAL_PREPASS_FORMAT,
+        NormalPrepass, Opaque3dPrepass,
+    },
     ecs::{
-        query::ROQueryItem,
+        query::{Has, ROQueryItem},
         system::{
             lifetimeless::{Read, SRes},
             SystemParamItem,
@@ -12,22 +16,24 @@ use bevy::{
     reflect::{TypePath, TypeUuid},
     render::{
         extract_component::{ComponentUniforms, DynamicUniformIndex, UniformComponentPlugin},
-        render_asset::{RenderAsset, RenderAssetPlugin, RenderAssets},
-        render_phase::{PhaseItem, RenderCommand, RenderCommandResult, TrackedRenderPass},
+        render_phase::{
+            AddRenderCommand, DrawFunctions, PhaseItem, RenderCommand, RenderCommandResult,
+            RenderPhase, SetItemPipeline, TrackedRenderPass,
+        },
         render_resource::*,
-        renderer::RenderDevice,
+        renderer::{RenderDevice, RenderQueue},
         texture::BevyDefault,
-        view::{ViewUniform, ViewUniforms},
+        view::{ExtractedView, RenderLayers, ViewUniform, ViewUniformOffset, ViewUniforms},
         Extract, Render, RenderApp, RenderSet,
     },
+    utils::{HashMap, HashSet},
 };
 
 pub struct PolylineBasePlugin;
 
 impl Plugin for PolylineBasePlugin {
     fn build(&self, app: &mut App) {
-        app.add_asset::<Polyline>()
-            .add_plugins(RenderAssetPlugin::<Polyline>::default());
+        app.add_asset::<Polyline>();
     }
 }
 
@@ -40,12 +46,18 @@ impl Plugin for PolylineRenderPlugin {
     fn finish(&self, app: &mut App) {
         app.sub_app_mut(RenderApp)
             .init_resource::<PolylinePipeline>()
-            .add_systems(ExtractSchedule, extract_polylines)
+            .init_resource::<SpecializedRenderPipelines<PolylinePipeline>>()
+            .init_resource::<PolylineBuffers>()
+            .init_resource::<ExtractedPolylines>()
+            .add_render_command::<Opaque3dPrepass, DrawPolylinePrepass>()
+            .add_systems(ExtractSchedule, (extract_polylines, extract_polyline_assets))
             .add_systems(
                 Render,
                 (
+                    prepare_polyline_buffers.in_set(RenderSet::Prepare),
                     queue_polyline_bind_group.in_set(RenderSet::Queue),
                     queue_polyline_view_bind_groups.in_set(RenderSet::Queue),
+                    queue_polyline_prepass.in_set(RenderSet::Queue),
                 ),
             );
     }
@@ -67,37 +79,243 @@ pub struct PolylineBundle {
 #[uuid = "c76af88a-8afe-405c-9a64-0a7d845d2546"]
 pub struct Polyline {
     pub vertices: Vec<Vec3>,
+    /// Per-vertex color, interpolated across each segment. Must be either empty (no
+    /// per-vertex color) or the same length as `vertices`; a mismatched length is treated
+    /// as empty.
+    pub colors: Vec<Vec4>,
+    /// Per-vertex width, interpolated across each segment. Must be either empty (no
+    /// per-vertex width) or the same length as `vertices`; a mismatched length is treated
+    /// as empty.
+    pub widths: Vec<f32>,
+    /// Caps the number of retained vertices for streaming/trail use cases: once
+    /// [`Polyline::push`] or [`Polyline::extend`] would exceed this, the oldest vertices
+    /// (and colors/widths, if present) are dropped to make room.
+    pub max_vertices: Option<usize>,
+}
+
+impl Polyline {
+    /// Appends a single vertex, dropping the oldest vertex first if `max_vertices` would
+    /// otherwise be exceeded.
+    pub fn push(&mut self, vertex: Vec3) {
+        self.vertices.push(vertex);
+        self.truncate_to_max_vertices();
+    }
+
+    /// Appends multiple vertices, dropping the oldest vertices first if `max_vertices`
+    /// would otherwise be exceeded.
+    pub fn extend(&mut self, vertices: impl IntoIterator<Item = Vec3>) {
+        self.vertices.extend(vertices);
+        self.truncate_to_max_vertices();
+    }
+
+    fn truncate_to_max_vertices(&mut self) {
+        let Some(max_vertices) = self.max_vertices else {
+            return;
+        };
+        if self.vertices.len() <= max_vertices {
+            return;
+        }
+        let excess = self.vertices.len() - max_vertices;
+        self.vertices.drain(0..excess);
+        if self.colors.len() >= excess {
+            self.colors.drain(0..excess);
+        }
+        if self.widths.len() >= excess {
+            self.widths.drain(0..excess);
+        }
+    }
+}
+
+/// Polylines that changed or were removed since the last extraction, read by
+/// [`prepare_polyline_buffers`]. Populated from `AssetEvent<Polyline>` rather than the
+/// `RenderAsset` trait so that `prepare_polyline_buffers` can see the [`GpuPolyline`] it
+/// produced on a previous frame and reuse its buffer instead of reallocating unconditionally.
+#[derive(Resource, Default)]
+pub struct ExtractedPolylines {
+    extracted: Vec<(Handle<Polyline>, Polyline)>,
+    removed: Vec<Handle<Polyline>>,
+}
+
+pub fn extract_polyline_assets(
+    mut events: Extract<EventReader<AssetEvent<Polyline>>>,
+    assets: Extract<Res<Assets<Polyline>>>,
+    mut extracted_polylines: ResMut<ExtractedPolylines>,
+) {
+    let mut changed = HashSet::default();
+    let mut removed = Vec::new();
+    for event in events.iter() {
+        match event {
+            AssetEvent::Created { handle } | AssetEvent::Modified { handle } => {
+                changed.insert(handle.clone_weak());
+            }
+            AssetEvent::Removed { handle } => {
+                changed.remove(handle);
+                removed.push(handle.clone_weak());
+            }
+        }
+    }
+
+    let mut extracted = Vec::with_capacity(changed.len());
+    for handle in changed {
+        if let Some(polyline) = assets.get(&handle) {
+            extracted.push((handle, polyline.clone()));
+        }
+    }
+
+    *extracted_polylines = ExtractedPolylines { extracted, removed };
 }
 
-impl RenderAsset for Polyline {
-    type ExtractedAsset = Polyline;
+/// An entry in [`PolylineBuffers`]: the GPU buffer alongside the CPU-side [`Polyline`] data
+/// it was built from, so [`prepare_polyline_buffers`] can tell a genuine append (the old
+/// data is an unchanged prefix of the new) from an edit or replacement that happens to
+/// leave the vertex count unchanged or larger.
+struct PolylineBufferEntry {
+    gpu: GpuPolyline,
+    cpu: Polyline,
+}
 
-    type PreparedAsset = GpuPolyline;
+/// The render-world GPU buffers backing each [`Polyline`] asset, keyed by handle and kept
+/// up to date by [`prepare_polyline_buffers`].
+#[derive(Resource, Default)]
+pub struct PolylineBuffers(HashMap<Handle<Polyline>, PolylineBufferEntry>);
 
-    type Param = SRes<RenderDevice>;
+impl PolylineBuffers {
+    pub fn get(&self, handle: &Handle<Polyline>) -> Option<&GpuPolyline> {
+        self.0.get(handle).map(|entry| &entry.gpu)
+    }
+}
 
-    fn extract_asset(&self) -> Self::ExtractedAsset {
-        self.clone()
+fn vertex_bytes(polyline: &Polyline, i: usize, has_colors: bool, has_widths: bool, data: &mut Vec<u8>) {
+    let position = polyline.vertices[i];
+    data.extend_from_slice(&position.x.to_le_bytes());
+    data.extend_from_slice(&position.y.to_le_bytes());
+    data.extend_from_slice(&position.z.to_le_bytes());
+    if has_colors {
+        let color = polyline.colors[i];
+        data.extend_from_slice(&color.x.to_le_bytes());
+        data.extend_from_slice(&color.y.to_le_bytes());
+        data.extend_from_slice(&color.z.to_le_bytes());
+        data.extend_from_slice(&color.w.to_le_bytes());
     }
+    if has_widths {
+        data.extend_from_slice(&polyline.widths[i].to_le_bytes());
+    }
+}
 
-    fn prepare_asset(
-        polyline: Self::ExtractedAsset,
-        render_device: &mut bevy::ecs::system::SystemParamItem<Self::Param>,
-    ) -> Result<
-        Self::PreparedAsset,
-        bevy::render::render_asset::PrepareAssetError<Self::ExtractedAsset>,
-    > {
-        let vertex_buffer_data = cast_slice(polyline.vertices.as_slice());
-        let vertex_buffer = render_device.create_buffer_with_data(&BufferInitDescriptor {
-            usage: BufferUsages::VERTEX,
-            label: Some("Polyline Vertex Buffer"),
-            contents: vertex_buffer_data,
+/// Encodes vertices `range` of `polyline` into the interleaved GPU vertex format.
+fn build_vertex_buffer_data(
+    polyline: &Polyline,
+    range: std::ops::Range<usize>,
+    has_colors: bool,
+    has_widths: bool,
+) -> Vec<u8> {
+    if !has_colors && !has_widths {
+        return cast_slice(&polyline.vertices[range]).to_vec();
+    }
+
+    let stride = 12 + if has_colors { 16 } else { 0 } + if has_widths { 4 } else { 0 };
+    let mut data = Vec::with_capacity(range.len() * stride);
+    for i in range {
+        vertex_bytes(polyline, i, has_colors, has_widths, &mut data);
+    }
+    data
+}
+
+/// Whether `polyline`'s first `previous.vertices.len()` vertices (and colors/widths, if
+/// `has_colors`/`has_widths`) compare equal (`==`) to the data `previous` was built from,
+/// i.e. everything since then is a pure append with no edits to already-uploaded vertices.
+/// Callers only invoke this once `has_colors`/`has_widths` are already known to match
+/// between `previous` and `polyline` (see `fits_existing_capacity` in
+/// [`prepare_polyline_buffers`]), so `previous`'s color/width vectors are exactly as long as
+/// `previous.vertices` whenever the respective flag is set.
+fn is_pure_append(previous: &Polyline, polyline: &Polyline, has_colors: bool, has_widths: bool) -> bool {
+    let prefix_len = previous.vertices.len();
+    polyline.vertices.len() >= prefix_len
+        && previous.vertices[..] == polyline.vertices[..prefix_len]
+        && (!has_colors || previous.colors[..] == polyline.colors[..prefix_len])
+        && (!has_widths || previous.widths[..] == polyline.widths[..prefix_len])
+}
+
+/// Turns extracted [`Polyline`] changes into GPU buffers, reusing the previous frame's
+/// allocation whenever possible instead of reallocating unconditionally:
+///
+/// - If the vertex layout (presence of per-vertex colors/widths) is unchanged, the new
+///   vertex count still fits in the existing `capacity`, and the new data is a verified
+///   pure append ([`is_pure_append`]) of the previous upload, only the newly-appended
+///   suffix is encoded and written with `write_buffer`.
+/// - If the layout is unchanged and it still fits but isn't a pure append (an in-place
+///   edit, for instance), the previous buffer is kept but fully re-encoded and re-uploaded.
+/// - Otherwise (first upload, layout change, or exceeding capacity) the buffer is
+///   reallocated at `vertex_count.max(1).next_power_of_two()` and fully uploaded.
+pub fn prepare_polyline_buffers(
+    mut buffers: ResMut<PolylineBuffers>,
+    mut extracted_polylines: ResMut<ExtractedPolylines>,
+    render_device: Res<RenderDevice>,
+    render_queue: Res<RenderQueue>,
+) {
+    for handle in extracted_polylines.removed.drain(..) {
+        buffers.0.remove(&handle);
+    }
+
+    for (handle, polyline) in extracted_polylines.extracted.drain(..) {
+        let vertex_count = polyline.vertices.len();
+        let has_colors = vertex_count > 0 && polyline.colors.len() == vertex_count;
+        let has_widths = vertex_count > 0 && polyline.widths.len() == vertex_count;
+        let stride = 12 + if has_colors { 16 } else { 0 } + if has_widths { 4 } else { 0 };
+
+        let previous = buffers.0.get(&handle);
+        let fits_existing_capacity = previous.is_some_and(|entry| {
+            entry.gpu.has_vertex_colors == has_colors
+                && entry.gpu.has_vertex_widths == has_widths
+                && vertex_count as u32 <= entry.gpu.capacity
         });
 
-        Ok(GpuPolyline {
-            vertex_buffer,
-            vertex_count: polyline.vertices.len() as u32,
-        })
+        let gpu = if fits_existing_capacity {
+            let previous = previous.unwrap();
+            if is_pure_append(&previous.cpu, &polyline, has_colors, has_widths) {
+                let suffix = build_vertex_buffer_data(
+                    &polyline,
+                    previous.cpu.vertices.len()..vertex_count,
+                    has_colors,
+                    has_widths,
+                );
+                let suffix_offset = previous.cpu.vertices.len() * stride;
+                render_queue.write_buffer(
+                    &previous.gpu.vertex_buffer,
+                    suffix_offset as BufferAddress,
+                    &suffix,
+                );
+            } else {
+                let data = build_vertex_buffer_data(&polyline, 0..vertex_count, has_colors, has_widths);
+                render_queue.write_buffer(&previous.gpu.vertex_buffer, 0, &data);
+            }
+            GpuPolyline {
+                vertex_buffer: previous.gpu.vertex_buffer.clone(),
+                vertex_count: vertex_count as u32,
+                capacity: previous.gpu.capacity,
+                has_vertex_colors: has_colors,
+                has_vertex_widths: has_widths,
+            }
+        } else {
+            let data = build_vertex_buffer_data(&polyline, 0..vertex_count, has_colors, has_widths);
+            let capacity = vertex_count.max(1).next_power_of_two();
+            let vertex_buffer = render_device.create_buffer(&BufferDescriptor {
+                label: Some("Polyline Vertex Buffer"),
+                size: (capacity * stride) as BufferAddress,
+                usage: BufferUsages::VERTEX | BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            });
+            render_queue.write_buffer(&vertex_buffer, 0, &data);
+            GpuPolyline {
+                vertex_buffer,
+                vertex_count: vertex_count as u32,
+                capacity: capacity as u32,
+                has_vertex_colors: has_colors,
+                has_vertex_widths: has_widths,
+            }
+        };
+
+        buffers.0.insert(handle, PolylineBufferEntry { gpu, cpu: polyline });
     }
 }
 
@@ -112,8 +330,32 @@ pub struct PolylineUniform {
 pub struct GpuPolyline {
     pub vertex_buffer: Buffer,
     pub vertex_count: u32,
+    /// The allocated vertex capacity of `vertex_buffer`, rounded up to the next power of
+    /// two of `vertex_count` as of the allocation that produced this buffer. Draw calls
+    /// still use `vertex_count`; [`prepare_polyline_buffers`] reuses `vertex_buffer` as long
+    /// as `vertex_count` stays within this capacity, only reallocating once it's exceeded.
+    pub capacity: u32,
+    /// Whether `vertex_buffer` was interleaved with per-vertex color, so the queued
+    /// [`PolylinePipelineKey`] for this polyline must set `VERTEX_COLORS`.
+    pub has_vertex_colors: bool,
+    /// Whether `vertex_buffer` was interleaved with per-vertex width, so the queued
+    /// [`PolylinePipelineKey`] for this polyline must set `VERTEX_WIDTHS`.
+    pub has_vertex_widths: bool,
 }
 
+/// Marker component for extracted polylines whose [`PolylineMaterial`] is fully opaque
+/// (`perspective == false` and `color` has no alpha), inserted by [`extract_polylines`].
+/// [`queue_polyline_prepass`] requires this: a perspective-faded or alpha-blended polyline
+/// must stay out of the prepass, since it always writes depth with
+/// `CompareFunction::Greater`/`depth_write_enabled: true` and would otherwise corrupt the
+/// depth buffer for the main pass' blending.
+#[derive(Component)]
+pub struct PolylineOpaque;
+
+/// Extracts visible polylines into the render world, along with the `RenderLayers` the
+/// entity belongs to (defaulting to layer 0) so queue systems can skip polylines whose
+/// layers don't intersect the view being queued for, and a [`PolylineOpaque`] marker when
+/// the polyline's material reports it's fully opaque.
 pub fn extract_polylines(
     mut commands: Commands,
     mut previous_len: Local<usize>,
@@ -123,23 +365,31 @@ pub fn extract_polylines(
             &ComputedVisibility,
             &GlobalTransform,
             &Handle<Polyline>,
+            &Handle<PolylineMaterial>,
+            Option<&RenderLayers>,
         )>,
     >,
+    materials: Extract<Res<Assets<PolylineMaterial>>>,
 ) {
     let mut values = Vec::with_capacity(*previous_len);
-    for (entity, computed_visibility, transform, handle) in query.iter() {
+    for (entity, computed_visibility, transform, handle, material_handle, render_layers) in query.iter() {
         if !computed_visibility.is_visible() {
             continue;
         }
         let transform = transform.compute_matrix();
+        let is_opaque = materials
+            .get(material_handle)
+            .is_some_and(|material| !material.perspective && material.color.a() >= 1.0);
         values.push((
             entity,
             (
                 handle.clone_weak(),
+                render_layers.copied().unwrap_or_default(),
                 PolylineUniform {
                     transform,
                     //inverse_transpose_model: transform.inverse().transpose(),
                 },
+                is_opaque.then_some(PolylineOpaque),
             ),
         ));
     }
@@ -193,23 +443,75 @@ impl FromWorld for PolylinePipeline {
     }
 }
 
+/// Builds the instanced vertex attributes shared by the main and prepass pipeline
+/// variants: position at locations 0/1, plus color at 2/3 and/or width at 4/5 when the
+/// key requests them. Returns the attributes alongside the resulting `array_stride`, so
+/// polylines with interleaved per-vertex data keep a layout consistent with how
+/// [`build_vertex_buffer_data`] interleaves the vertex buffer.
+fn polyline_vertex_layout(key: PolylinePipelineKey) -> (Vec<VertexAttribute>, BufferAddress) {
+    let has_vertex_colors = key.contains(PolylinePipelineKey::VERTEX_COLORS);
+    let has_vertex_widths = key.contains(PolylinePipelineKey::VERTEX_WIDTHS);
+    let array_stride = 12
+        + if has_vertex_colors { 16 } else { 0 }
+        + if has_vertex_widths { 4 } else { 0 };
+
+    let mut vertex_attributes = vec![
+        VertexAttribute {
+            format: VertexFormat::Float32x3,
+            offset: 0,
+            shader_location: 0,
+        },
+        VertexAttribute {
+            format: VertexFormat::Float32x3,
+            offset: array_stride,
+            shader_location: 1,
+        },
+    ];
+    if has_vertex_colors {
+        vertex_attributes.push(VertexAttribute {
+            format: VertexFormat::Float32x4,
+            offset: 12,
+            shader_location: 2,
+        });
+        vertex_attributes.push(VertexAttribute {
+            format: VertexFormat::Float32x4,
+            offset: 12 + array_stride,
+            shader_location: 3,
+        });
+    }
+    if has_vertex_widths {
+        let width_offset = 12 + if has_vertex_colors { 16 } else { 0 };
+        vertex_attributes.push(VertexAttribute {
+            format: VertexFormat::Float32,
+            offset: width_offset,
+            shader_location: 4,
+        });
+        vertex_attributes.push(VertexAttribute {
+            format: VertexFormat::Float32,
+            offset: width_offset + array_stride,
+            shader_location: 5,
+        });
+    }
+
+    (vertex_attributes, array_stride)
+}
+
 impl SpecializedRenderPipeline for PolylinePipeline {
     type Key = PolylinePipelineKey;
 
     fn specialize(&self, key: Self::Key) -> RenderPipelineDescriptor {
-        let vertex_attributes = vec![
-            VertexAttribute {
-                format: VertexFormat::Float32x3,
-                offset: 0,
-                shader_location: 0,
-            },
-            VertexAttribute {
-                format: VertexFormat::Float32x3,
-                offset: 12,
-                shader_location: 1,
-            },
-        ];
-        let shader_defs = Vec::new();
+        if key.intersects(PolylinePipelineKey::PREPASS_FLAGS) {
+            return self.specialize_prepass(key);
+        }
+
+        let (vertex_attributes, array_stride) = polyline_vertex_layout(key);
+        let mut shader_defs = Vec::new();
+        if key.contains(PolylinePipelineKey::VERTEX_COLORS) {
+            shader_defs.push("VERTEX_COLORS".into());
+        }
+        if key.contains(PolylinePipelineKey::VERTEX_WIDTHS) {
+            shader_defs.push("VERTEX_WIDTHS".into());
+        }
         let (label, blend, depth_write_enabled);
 
         if key.contains(PolylinePipelineKey::TRANSPARENT_MAIN_PASS) {
@@ -234,6 +536,8 @@ impl SpecializedRenderPipeline for PolylinePipeline {
             depth_write_enabled = true;
         }
 
+        let depth_compare = CompareFunction::Greater;
+
         let format = match key.contains(PolylinePipelineKey::HDR) {
             true => bevy::render::view::ViewTarget::TEXTURE_FORMAT_HDR,
             false => TextureFormat::bevy_default(),
@@ -245,7 +549,7 @@ impl SpecializedRenderPipeline for PolylinePipeline {
                 entry_point: "vertex".into(),
                 shader_defs: shader_defs.clone(),
                 buffers: vec![VertexBufferLayout {
-                    array_stride: 12,
+                    array_stride,
                     step_mode: VertexStepMode::Instance,
                     attributes: vertex_attributes,
                 }],
@@ -273,7 +577,7 @@ impl SpecializedRenderPipeline for PolylinePipeline {
             depth_stencil: Some(DepthStencilState {
                 format: TextureFormat::Depth32Float,
                 depth_write_enabled,
-                depth_compare: CompareFunction::Greater,
+                depth_compare,
                 stencil: StencilState {
                     front: StencilFaceState::IGNORE,
                     back: StencilFaceState::IGNORE,
@@ -297,6 +601,109 @@ impl SpecializedRenderPipeline for PolylinePipeline {
     }
 }
 
+impl PolylinePipeline {
+    /// Builds the depth/normal/motion-vector prepass variant of the polyline pipeline.
+    ///
+    /// Only opaque polylines are expected to be queued into the prepass (perspective
+    /// fading in the transparent pass would otherwise corrupt the depth buffer), so this
+    /// always writes depth using the same reverse-Z `CompareFunction::Greater` compare as
+    /// the main pass and never blends.
+    fn specialize_prepass(&self, key: PolylinePipelineKey) -> RenderPipelineDescriptor {
+        let (vertex_attributes, array_stride) = polyline_vertex_layout(key);
+
+        let mut shader_defs = Vec::new();
+        if key.contains(PolylinePipelineKey::VERTEX_COLORS) {
+            shader_defs.push("VERTEX_COLORS".into());
+        }
+        if key.contains(PolylinePipelineKey::VERTEX_WIDTHS) {
+            shader_defs.push("VERTEX_WIDTHS".into());
+        }
+        if key.contains(PolylinePipelineKey::DEPTH_PREPASS) {
+            shader_defs.push("DEPTH_PREPASS".into());
+        }
+        if key.contains(PolylinePipelineKey::NORMAL_PREPASS) {
+            shader_defs.push("NORMAL_PREPASS".into());
+        }
+        if key.contains(PolylinePipelineKey::MOTION_VECTOR_PREPASS) {
+            shader_defs.push("MOTION_VECTOR_PREPASS".into());
+        }
+
+        // Lines have no true surface normal, so the normal prepass target is filled with
+        // the view-facing bitangent of the segment quad as a stand-in.
+        let mut targets = Vec::new();
+        if key.contains(PolylinePipelineKey::NORMAL_PREPASS) {
+            targets.push(Some(ColorTargetState {
+                format: NORMAL_PREPASS_FORMAT,
+                blend: None,
+                write_mask: ColorWrites::ALL,
+            }));
+        }
+        if key.contains(PolylinePipelineKey::MOTION_VECTOR_PREPASS) {
+            targets.push(Some(ColorTargetState {
+                format: MOTION_VECTOR_PREPASS_FORMAT,
+                blend: None,
+                write_mask: ColorWrites::ALL,
+            }));
+        }
+
+        RenderPipelineDescriptor {
+            vertex: VertexState {
+                shader: SHADER_HANDLE.typed::<Shader>(),
+                entry_point: "vertex_prepass".into(),
+                shader_defs: shader_defs.clone(),
+                buffers: vec![VertexBufferLayout {
+                    array_stride,
+                    step_mode: VertexStepMode::Instance,
+                    attributes: vertex_attributes,
+                }],
+            },
+            fragment: Some(FragmentState {
+                shader: SHADER_HANDLE.typed::<Shader>(),
+                shader_defs,
+                entry_point: "fragment_prepass".into(),
+                targets,
+            }),
+            // Unlike `specialize()`, nothing wraps the prepass variant in a material
+            // pipeline that fills this in, so set it directly: `DrawPolylinePrepass` binds
+            // the view uniform at group 0 and the polyline uniform at group 1 via
+            // `SetPolylineViewBindGroup<0>`/`SetPolylineBindGroup<1>`.
+            layout: vec![self.view_layout.clone(), self.polyline_layout.clone()],
+            primitive: PrimitiveState {
+                front_face: FrontFace::Ccw,
+                cull_mode: None,
+                unclipped_depth: false,
+                polygon_mode: PolygonMode::Fill,
+                conservative: false,
+                topology: PrimitiveTopology::TriangleList,
+                strip_index_format: None,
+            },
+            depth_stencil: Some(DepthStencilState {
+                format: TextureFormat::Depth32Float,
+                depth_write_enabled: true,
+                depth_compare: CompareFunction::Greater,
+                stencil: StencilState {
+                    front: StencilFaceState::IGNORE,
+                    back: StencilFaceState::IGNORE,
+                    read_mask: 0,
+                    write_mask: 0,
+                },
+                bias: DepthBiasState {
+                    constant: 0,
+                    slope_scale: 0.0,
+                    clamp: 0.0,
+                },
+            }),
+            multisample: MultisampleState {
+                count: key.msaa_samples(),
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+            label: Some("polyline_prepass_pipeline"),
+            push_constant_ranges: vec![],
+        }
+    }
+}
+
 bitflags::bitflags! {
     #[repr(transparent)]
     #[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Clone, Copy)]
@@ -307,11 +714,22 @@ bitflags::bitflags! {
         const PERSPECTIVE = (1 << 0);
         const TRANSPARENT_MAIN_PASS = (1 << 1);
         const HDR = (1 << 2);
+        const DEPTH_PREPASS = (1 << 3);
+        const NORMAL_PREPASS = (1 << 4);
+        const MOTION_VECTOR_PREPASS = (1 << 5);
+        const VERTEX_COLORS = (1 << 6);
+        const VERTEX_WIDTHS = (1 << 7);
         const MSAA_RESERVED_BITS = Self::MSAA_MASK_BITS << Self::MSAA_SHIFT_BITS;
     }
 }
 
 impl PolylinePipelineKey {
+    /// Any of the prepass flags, i.e. the view carries a `DepthPrepass`, `NormalPrepass` or
+    /// `MotionVectorPrepass` component and wants polylines represented in it.
+    const PREPASS_FLAGS: Self = Self::from_bits_retain(
+        Self::DEPTH_PREPASS.bits() | Self::NORMAL_PREPASS.bits() | Self::MOTION_VECTOR_PREPASS.bits(),
+    );
+
     const MSAA_MASK_BITS: u32 = 0b111;
     const MSAA_SHIFT_BITS: u32 = 32 - Self::MSAA_MASK_BITS.count_ones();
 
@@ -362,6 +780,12 @@ pub fn queue_polyline_bind_group(
 #[derive(Component)]
 pub struct PolylineViewBindGroup {
     pub value: BindGroup,
+    /// The view's `RenderLayers`, defaulting to layer 0 if the camera has none. Mirrors the
+    /// check [`queue_polyline_prepass`] performs directly off the view entity's
+    /// `RenderLayers` component; kept here too so other queue systems that only have access
+    /// to this component can apply the same masking against a polyline's extracted
+    /// `RenderLayers` (see [`extract_polylines`]) without an extra query.
+    pub render_layers: RenderLayers,
 }
 
 #[allow(clippy::too_many_arguments)]
@@ -370,10 +794,10 @@ pub fn queue_polyline_view_bind_groups(
     render_device: Res<RenderDevice>,
     polyline_pipeline: Res<PolylinePipeline>,
     view_uniforms: Res<ViewUniforms>,
-    views: Query<Entity, With<bevy::render::view::ExtractedView>>,
+    views: Query<(Entity, Option<&RenderLayers>), With<bevy::render::view::ExtractedView>>,
 ) {
     if let Some(view_binding) = view_uniforms.uniforms.binding() {
-        for entity in views.iter() {
+        for (entity, render_layers) in views.iter() {
             let view_bind_group = render_device.create_bind_group(&BindGroupDescriptor {
                 entries: &[BindGroupEntry {
                     binding: 0,
@@ -385,11 +809,118 @@ pub fn queue_polyline_view_bind_groups(
 
             commands.entity(entity).insert(PolylineViewBindGroup {
                 value: view_bind_group,
+                render_layers: render_layers.copied().unwrap_or_default(),
             });
         }
     }
 }
 
+/// Queues extracted polylines into the depth/normal/motion-vector prepass for every view
+/// that requests it, i.e. carries a `DepthPrepass`, `NormalPrepass` or
+/// `MotionVectorPrepass` component. Only polylines carrying [`PolylineOpaque`] (see
+/// [`extract_polylines`]) are queued: this pass always writes depth with
+/// `CompareFunction::Greater`/`depth_write_enabled: true`, so a perspective-faded or
+/// alpha-blended polyline would corrupt the depth buffer the main pass blends against.
+///
+/// Polylines whose extracted [`RenderLayers`] don't intersect the view's are skipped, so a
+/// polyline on a layer the camera doesn't watch is never queued into that view's prepass.
+#[allow(clippy::too_many_arguments)]
+pub fn queue_polyline_prepass(
+    draw_functions: Res<DrawFunctions<Opaque3dPrepass>>,
+    polyline_pipeline: Res<PolylinePipeline>,
+    mut pipelines: ResMut<SpecializedRenderPipelines<PolylinePipeline>>,
+    pipeline_cache: Res<PipelineCache>,
+    msaa: Res<Msaa>,
+    gpu_polylines: Res<PolylineBuffers>,
+    polylines: Query<
+        (Entity, &Handle<Polyline>, &RenderLayers, &PolylineUniform),
+        With<PolylineOpaque>,
+    >,
+    mut views: Query<(
+        &ExtractedView,
+        Option<&RenderLayers>,
+        &mut RenderPhase<Opaque3dPrepass>,
+        Has<DepthPrepass>,
+        Has<NormalPrepass>,
+        Has<MotionVectorPrepass>,
+    )>,
+) {
+    let draw_function = draw_functions.read().id::<DrawPolylinePrepass>();
+
+    for (
+        view,
+        view_layers,
+        mut prepass_phase,
+        has_depth_prepass,
+        has_normal_prepass,
+        has_motion_vector_prepass,
+    ) in &mut views
+    {
+        if !(has_depth_prepass || has_normal_prepass || has_motion_vector_prepass) {
+            continue;
+        }
+        let view_layers = view_layers.copied().unwrap_or_default();
+        let rangefinder = view.rangefinder3d();
+
+        let mut view_key =
+            PolylinePipelineKey::from_msaa_samples(msaa.samples()) | PolylinePipelineKey::from_hdr(view.hdr);
+        if has_depth_prepass {
+            view_key |= PolylinePipelineKey::DEPTH_PREPASS;
+        }
+        if has_normal_prepass {
+            view_key |= PolylinePipelineKey::NORMAL_PREPASS;
+        }
+        if has_motion_vector_prepass {
+            view_key |= PolylinePipelineKey::MOTION_VECTOR_PREPASS;
+        }
+
+        for (entity, handle, polyline_layers, uniform) in &polylines {
+            if !view_layers.intersects(polyline_layers) {
+                continue;
+            }
+            let Some(gpu_polyline) = gpu_polylines.get(handle) else {
+                continue;
+            };
+
+            let mut key = view_key;
+            if gpu_polyline.has_vertex_colors {
+                key |= PolylinePipelineKey::VERTEX_COLORS;
+            }
+            if gpu_polyline.has_vertex_widths {
+                key |= PolylinePipelineKey::VERTEX_WIDTHS;
+            }
+
+            let pipeline = pipelines.specialize(&pipeline_cache, &polyline_pipeline, key);
+            let translation = uniform.transform.transform_point3(Vec3::ZERO);
+            prepass_phase.add(Opaque3dPrepass {
+                entity,
+                pipeline,
+                draw_function,
+                distance: rangefinder.distance(&translation),
+            });
+        }
+    }
+}
+
+pub struct SetPolylineViewBindGroup<const I: usize>;
+impl<const I: usize, P: PhaseItem> RenderCommand<P> for SetPolylineViewBindGroup<I> {
+    type ViewWorldQuery = (Read<ViewUniformOffset>, Read<PolylineViewBindGroup>);
+    type ItemWorldQuery = ();
+    type Param = ();
+
+    #[inline]
+    fn render<'w>(
+        _item: &P,
+        (view_uniform, view_bind_group): ROQueryItem<'w, Self::ViewWorldQuery>,
+        _entity: ROQueryItem<'w, Self::ItemWorldQuery>,
+        _param: SystemParamItem<'w, '_, Self::Param>,
+        pass: &mut TrackedRenderPass<'w>,
+    ) -> RenderCommandResult {
+        pass.set_bind_group(I, &view_bind_group.value, &[view_uniform.offset]);
+        RenderCommandResult::Success
+    }
+}
+
 pub struct SetPolylineBindGroup<const I: usize>;
 impl<const I: usize, P: PhaseItem> RenderCommand<P> for SetPolylineBindGroup<I> {
     type ViewWorldQuery = ();
@@ -413,7 +944,7 @@ pub struct DrawPolyline;
 impl<P: PhaseItem> RenderCommand<P> for DrawPolyline {
     type ViewWorldQuery = ();
     type ItemWorldQuery = Read<Handle<Polyline>>;
-    type Param = SRes<RenderAssets<Polyline>>;
+    type Param = SRes<PolylineBuffers>;
 
     #[inline]
     fn render<'w>(
@@ -433,3 +964,13 @@ impl<P: PhaseItem> RenderCommand<P> for DrawPolyline {
         }
     }
 }
+
+/// Render command set used to draw polylines into the prepass, registered onto
+/// [`Opaque3dPrepass`] by [`PolylineRenderPlugin::finish`] and queued by
+/// [`queue_polyline_prepass`].
+pub type DrawPolylinePrepass = (
+    SetItemPipeline,
+    SetPolylineViewBindGroup<0>,
+    SetPolylineBindGroup<1>,
+    DrawPolyline,
+);